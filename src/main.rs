@@ -1,10 +1,12 @@
-use regex::{Captures, Regex};
+use regex::{Captures, Regex, RegexBuilder};
 use std::env;
 use std::fs::{read_dir, File};
 use std::io::{self, BufRead};
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
+use std::sync::Arc;
+use std::thread;
 
 const BOLD_START: &str = "\x1b[1m";
 const BOLD_END: &str = "\x1b[0m";
@@ -17,68 +19,397 @@ struct Occurance {
     line_content: String,
 }
 
-fn highlight_text(line: &str, highlight_text: &str) -> String {
-    let regex_pattern = format!(r"(?i){}", regex::escape(highlight_text));
-    let re = Regex::new(&regex_pattern).unwrap();
+/// A search term together with the regex that should be matched against each line.
+///
+/// In literal mode `raw` is escaped before being compiled; in `--regex` mode `raw` is
+/// compiled as-is, so it is kept around separately for display and for the `Occurance`
+/// records.
+struct SearchTerm {
+    raw: String,
+    regex: Regex,
+}
+
+fn build_search_term(
+    raw: &str,
+    check_case: bool,
+    use_regex: bool,
+) -> Result<SearchTerm, regex::Error> {
+    let pattern = if use_regex {
+        raw.to_string()
+    } else {
+        regex::escape(raw)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!check_case)
+        .build()?;
+    Ok(SearchTerm {
+        raw: raw.to_string(),
+        regex,
+    })
+}
 
+fn highlight_text(line: &str, re: &Regex) -> String {
     re.replace_all(line, |captures: &Captures| {
         format!("{}{}{}", BOLD_START, &captures[0], BOLD_END)
     })
     .to_string()
 }
 
-fn files_in_dir(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+/// One `.gitignore`/`.ignore` entry. `negate` records a leading `!`, which re-includes a
+/// path that an earlier pattern excluded. `anchored` records a leading `/`, which means
+/// the pattern only matches an immediate child of the directory the entry was read from,
+/// rather than a same-named entry at any depth below it.
+struct IgnorePattern {
+    pattern: String,
+    negate: bool,
+    anchored: bool,
+}
+
+fn parse_ignore_file(path: &Path) -> Vec<IgnorePattern> {
+    let mut patterns = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let reader = io::BufReader::new(file);
+        // `.flatten()` skips lines that fail to decode (e.g. invalid UTF-8) one at a
+        // time and keeps reading; `map_while(Result::ok)` would stop at the first such
+        // line and silently drop every pattern after it. A `BufReader::lines()` over a
+        // real file doesn't repeatedly error forever the way clippy's lint assumes, so
+        // we deliberately keep `.flatten()` here.
+        #[allow(clippy::lines_filter_map_ok)]
+        for line in reader.lines().flatten() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negate, rest) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (anchored, rest) = match rest.strip_prefix('/') {
+                Some(rest) => (true, rest),
+                None => (false, rest),
+            };
+            patterns.push(IgnorePattern {
+                pattern: rest.trim_end_matches('/').to_string(),
+                negate,
+                anchored,
+            });
+        }
+    }
+    patterns
+}
+
+/// A small gitignore-style glob matcher: `*` matches any run of characters other than
+/// `/`, `?` matches a single character other than `/`, anything else matches literally.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn helper(p: &[u8], t: &[u8]) -> bool {
+        if p.is_empty() {
+            return t.is_empty();
+        }
+        match p[0] {
+            b'*' => {
+                for i in 0..=t.len() {
+                    if !t[..i].contains(&b'/') && helper(&p[1..], &t[i..]) {
+                        return true;
+                    }
+                }
+                false
+            }
+            b'?' => !t.is_empty() && t[0] != b'/' && helper(&p[1..], &t[1..]),
+            c => !t.is_empty() && t[0] == c && helper(&p[1..], &t[1..]),
+        }
+    }
+    helper(pattern.as_bytes(), text.as_bytes())
+}
+
+/// Is `name` excluded by the accumulated ignore layers, searching from the deepest
+/// directory upward and taking the first pattern (within a directory, the last one
+/// listed) that matches? `name`'s parent is always the directory that owns the last
+/// layer, so an `anchored` pattern from an ancestor layer refers to a different,
+/// shallower directory and is skipped there.
+fn is_ignored(layers: &[Vec<IgnorePattern>], name: &str) -> bool {
+    for (depth, layer) in layers.iter().enumerate().rev() {
+        let is_own_layer = depth == layers.len() - 1;
+        for pat in layer.iter().rev() {
+            if pat.anchored && !is_own_layer {
+                continue;
+            }
+            if glob_match(&pat.pattern, name) {
+                return !pat.negate;
+            }
+        }
+    }
+    false
+}
+
+fn walk_dir(
+    dir: &Path,
+    include_hidden: bool,
+    layers: &mut Vec<Vec<IgnorePattern>>,
+) -> std::io::Result<Vec<PathBuf>> {
     let mut files: Vec<PathBuf> = Vec::new();
 
+    let mut layer = parse_ignore_file(&dir.join(".gitignore"));
+    layer.extend(parse_ignore_file(&dir.join(".ignore")));
+    layers.push(layer);
+
     for entry in read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if is_ignored(layers, name) {
+            continue;
+        }
+
         if path.is_file() {
             files.push(path);
         } else if path.is_dir() {
-            match files_in_dir(path.as_path()) {
-                Ok(entries) => files.extend(entries),
-                _ => {} // Don't check any files that cause errors when checking if they are a file
-            };
+            if name == ".git" || (!include_hidden && name.starts_with('.')) {
+                continue;
+            }
+            // Don't check any files that cause errors when checking if they are a file
+            if let Ok(entries) = walk_dir(path.as_path(), include_hidden, layers) {
+                files.extend(entries);
+            }
         }
     }
 
+    layers.pop();
     Ok(files)
 }
 
+fn files_in_dir(dir: &Path, include_hidden: bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut layers: Vec<Vec<IgnorePattern>> = Vec::new();
+    walk_dir(dir, include_hidden, &mut layers)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    NdJson,
+}
+
+/// Escape a string for embedding in a JSON string literal.
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn occurance_to_json(result: &Occurance, column: Option<usize>) -> String {
+    let column = column
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| "null".to_string());
+    format!(
+        "{{\"filename\":\"{}\",\"line_number\":{},\"target_string\":\"{}\",\"column\":{},\"line_content\":\"{}\"}}",
+        json_escape(&result.filename),
+        result.line_number,
+        json_escape(&result.target_string),
+        column,
+        json_escape(&result.line_content),
+    )
+}
+
+/// A known-acceptable occurrence read from a `.wordwarden-allow` file: either a specific
+/// `path:line:word` tuple, or a `glob:word` pair that allows `word` in any matching path.
+enum AllowEntry {
+    Exact {
+        path: String,
+        line: usize,
+        word: String,
+    },
+    Glob {
+        glob: String,
+        word: String,
+    },
+}
+
+fn parse_allow_line(line: &str) -> Option<AllowEntry> {
+    let parts: Vec<&str> = line.splitn(3, ':').collect();
+    match parts.as_slice() {
+        [path, line_str, word] => line_str
+            .parse::<usize>()
+            .ok()
+            .map(|line| AllowEntry::Exact {
+                path: path.to_string(),
+                line,
+                word: word.to_string(),
+            }),
+        [glob, word] => Some(AllowEntry::Glob {
+            glob: glob.to_string(),
+            word: word.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+fn parse_allow_file(path: &Path) -> Vec<AllowEntry> {
+    let mut entries = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let reader = io::BufReader::new(file);
+        // See the matching comment in `parse_ignore_file`: `.flatten()` skips individual
+        // bad lines instead of giving up on the rest of the file.
+        #[allow(clippy::lines_filter_map_ok)]
+        for line in reader.lines().flatten() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(entry) = parse_allow_line(line) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+fn is_allowed(allowlist: &[AllowEntry], filename: &str, line_number: usize, word: &str) -> bool {
+    allowlist.iter().any(|entry| match entry {
+        AllowEntry::Exact {
+            path,
+            line,
+            word: w,
+        } => path == filename && *line == line_number && w == word,
+        AllowEntry::Glob { glob, word: w } => w == word && glob_match(glob, filename),
+    })
+}
+
+/// Read the `wordwarden:ignore-next-line` and `wordwarden:ignore word1,word2` directives
+/// from a line, returning (should the *next* line be skipped entirely, words ignored on
+/// *this* line).
+fn line_directives(line: &str) -> (bool, Vec<String>) {
+    if line.contains("wordwarden:ignore-next-line") {
+        return (true, Vec::new());
+    }
+    if let Some(idx) = line.find("wordwarden:ignore") {
+        let rest = line[idx + "wordwarden:ignore".len()..].trim_start();
+        let words = rest
+            .split(',')
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty())
+            .collect();
+        return (false, words);
+    }
+    (false, Vec::new())
+}
+
+/// Does `term` match `line`, honoring `whole_line` (match must span the entire trimmed
+/// line rather than just a substring)?
+fn term_matches(term: &SearchTerm, line: &str, whole_line: bool) -> bool {
+    if whole_line {
+        let trimmed = line.trim();
+        match term.regex.find(trimmed) {
+            Some(m) => m.start() == 0 && m.end() == trimmed.len(),
+            None => false,
+        }
+    } else {
+        term.regex.is_match(line)
+    }
+}
+
+/// Is `term` satisfied on `line`, i.e. either suppressed by an ignore directive/allowlist
+/// entry or actually present? Used to decide, per `--invert` line, whether a given target
+/// can be counted as accounted for.
+fn term_satisfied(
+    term: &SearchTerm,
+    line: &str,
+    whole_line: bool,
+    ignored_words: &[String],
+    allowlist: &[AllowEntry],
+    filename_str: &str,
+    line_number: usize,
+) -> bool {
+    ignored_words.iter().any(|word| word == &term.raw)
+        || is_allowed(allowlist, filename_str, line_number, &term.raw)
+        || term_matches(term, line, whole_line)
+}
+
 fn check_file(
     filename: &Path,
     results: &mut Vec<Occurance>,
-    target: &str,
-    check_case: bool,
+    terms: &[SearchTerm],
     escape: &str,
+    invert: bool,
+    whole_line: bool,
+    allowlist: &[AllowEntry],
 ) -> io::Result<bool> {
     let file = File::open(filename)?;
     let reader = io::BufReader::new(file);
     let mut found = false;
+    let filename_str = filename.to_str().unwrap().to_owned();
+    let mut skip_next_line = false;
 
     for (index, line) in reader.lines().enumerate() {
         if let Ok(line) = line {
-            let mut target_in_line: bool;
-            if line.contains(escape) {
+            let line_number = index + 1;
+            let skip_this_line = skip_next_line;
+            let (ignore_next_line, ignored_words) = line_directives(&line);
+            skip_next_line = ignore_next_line;
+
+            if skip_this_line || line.contains(escape) {
                 continue;
             }
-            if check_case {
-                target_in_line = line.contains(target);
+
+            if invert {
+                // Flag the line only if none of the targets are accounted for, matching
+                // `grep -v` with multiple patterns rather than checking each target in
+                // isolation.
+                let any_satisfied = terms.iter().any(|term| {
+                    term_satisfied(
+                        term,
+                        &line,
+                        whole_line,
+                        &ignored_words,
+                        allowlist,
+                        &filename_str,
+                        line_number,
+                    )
+                });
+                if !any_satisfied {
+                    let target_string = terms
+                        .iter()
+                        .map(|term| term.raw.as_str())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    results.push(Occurance {
+                        filename: filename_str.clone(),
+                        line_number,
+                        target_string,
+                        line_content: line,
+                    });
+                    found = true;
+                }
             } else {
-                let regex_pattern = format!(r"(?i){}", regex::escape(target));
-                let re = Regex::new(&regex_pattern).unwrap();
-                target_in_line = re.is_match(&line);
-            }
-            if target_in_line {
-                let occurance = Occurance {
-                    filename: filename.to_str().unwrap().to_owned(),
-                    line_number: index + 1,
-                    target_string: target.to_string(),
-                    line_content: line,
-                };
-                results.push(occurance);
-                found = true;
+                for term in terms {
+                    if ignored_words.iter().any(|word| word == &term.raw)
+                        || is_allowed(allowlist, &filename_str, line_number, &term.raw)
+                    {
+                        continue;
+                    }
+                    if term_matches(term, &line, whole_line) {
+                        results.push(Occurance {
+                            filename: filename_str.clone(),
+                            line_number,
+                            target_string: term.raw.clone(),
+                            line_content: line.clone(),
+                        });
+                        found = true;
+                    }
+                }
             }
         }
     }
@@ -86,6 +417,178 @@ fn check_file(
     Ok(found)
 }
 
+/// Scan one worker's slice of `paths` against every search term, exiting the process on
+/// the first unreadable file (matching `check_file`'s existing error handling).
+fn scan_chunk(
+    paths: &[PathBuf],
+    search_terms: &[SearchTerm],
+    escape: &str,
+    invert: bool,
+    whole_line: bool,
+    allowlist: &[AllowEntry],
+) -> (Vec<Occurance>, bool) {
+    let mut results = Vec::new();
+    let mut found_any = false;
+    for path in paths {
+        match check_file(
+            path,
+            &mut results,
+            search_terms,
+            escape,
+            invert,
+            whole_line,
+            allowlist,
+        ) {
+            Ok(found) => {
+                if found {
+                    found_any = true;
+                }
+            }
+            Err(err) => {
+                eprintln!("Error reading '{}': {}", path.to_str().unwrap_or("?"), err);
+                process::exit(2);
+            }
+        }
+    }
+    (results, found_any)
+}
+
+/// An operand that hasn't yet been resolved to a file path or a search word.
+///
+/// `Auto` operands are resolved by checking the filesystem, matching the historical
+/// behavior; `Word` operands (from `-w` or from anything after `--`) are always treated
+/// as search words, even if a file or directory of that name happens to exist.
+enum Operand {
+    Auto(String),
+    Word(String),
+}
+
+struct Config {
+    filepaths: Vec<PathBuf>,
+    search_strings: Vec<String>,
+    check_case: bool,
+    use_regex: bool,
+    invert: bool,
+    whole_line: bool,
+    threads: Option<usize>,
+    output_format: OutputFormat,
+    escape: String,
+}
+
+/// Parse `argv[1..]` into a `Config`, separating options from path/word operands.
+/// Everything after a bare `--` is treated as a search word, never as a path.
+fn parse_args(args: &[String]) -> Config {
+    let mut check_case = false;
+    let mut use_regex = false;
+    let mut invert = false;
+    let mut whole_line = false;
+    let mut include_hidden = false;
+    let mut threads: Option<usize> = None;
+    let mut output_format = OutputFormat::Text;
+    let mut escape = "wordwarden:skip-line".to_string();
+    let mut operands: Vec<Operand> = Vec::new();
+    let mut words_only = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if words_only {
+            operands.push(Operand::Word(arg.clone()));
+            i += 1;
+            continue;
+        }
+        match arg.as_str() {
+            "--" => words_only = true,
+            "--casecheck" => check_case = true,
+            "--no-casecheck" => check_case = false,
+            "--regex" => use_regex = true,
+            "--invert" | "-v" => invert = true,
+            "--whole-line" | "-x" => whole_line = true,
+            "--hidden" => include_hidden = true,
+            "-w" => {
+                i += 1;
+                match args.get(i) {
+                    Some(word) => operands.push(Operand::Word(word.clone())),
+                    None => {
+                        eprintln!("-w must be followed by a word to search for");
+                        process::exit(2);
+                    }
+                }
+            }
+            _ if arg.starts_with("--escape=") => {
+                escape = arg["--escape=".len()..].to_string();
+            }
+            _ if arg.starts_with("--output=") => {
+                output_format = match &arg["--output=".len()..] {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    "ndjson" => OutputFormat::NdJson,
+                    other => {
+                        eprintln!(
+                            "Invalid value '{}' for --output, expected 'text', 'json' or 'ndjson'",
+                            other
+                        );
+                        process::exit(2);
+                    }
+                };
+            }
+            _ if arg.starts_with("--threads=") => {
+                match arg["--threads=".len()..].parse::<usize>() {
+                    Ok(value) if value > 0 => threads = Some(value),
+                    _ => {
+                        eprintln!("Invalid value for --threads, expected a positive integer");
+                        process::exit(2);
+                    }
+                }
+            }
+            _ => operands.push(Operand::Auto(arg.clone())),
+        }
+        i += 1;
+    }
+
+    let mut filepaths: Vec<PathBuf> = Vec::new();
+    let mut search_strings: Vec<String> = Vec::new();
+    for operand in operands {
+        match operand {
+            Operand::Word(word) => search_strings.push(word),
+            Operand::Auto(raw) => {
+                let path = Path::new(&raw);
+                if path.is_file() {
+                    // For better integration with pre-commit, don't check the
+                    // .pre-commit-config.yaml for occurences because by the way the hook is
+                    // set up, you specify the arguments to this package in that file. If we
+                    // did not hardcode it here every user would need to use an escape entry
+                    // in that config file.
+                    if (path.file_name().unwrap() != ".pre-commit-config.yaml")
+                        && (path.file_name().unwrap() != ".pre-commit-config.yml")
+                    {
+                        filepaths.push(path.to_path_buf());
+                    }
+                } else if path.is_dir() {
+                    // Don't check any files that cause errors when checking if they are a file
+                    if let Ok(entries) = files_in_dir(path, include_hidden) {
+                        filepaths.extend(entries);
+                    }
+                } else {
+                    search_strings.push(raw);
+                }
+            }
+        }
+    }
+
+    Config {
+        filepaths,
+        search_strings,
+        check_case,
+        use_regex,
+        invert,
+        whole_line,
+        threads,
+        output_format,
+        escape,
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -100,92 +603,449 @@ fn main() {
             Use -w to force the interpretation of the argument as a word if it also happens to be the name of
                 a file or directory. This might look like: {} examples -w 'examples'
                 In that example we check for the word 'examples' in the files in the folder called examples
+            Use -- to force every argument after it to be treated as a search word, regardless
+                of whether it happens to match a file or directory on disk.
+            Use --regex to interpret each search word as a regular expression instead of a literal
+                string, e.g. {} --regex 'TODO\\(.*\\)'
+            Use --invert or -v to flag lines that do NOT contain any of the search words, useful
+                for enforcing that a required word (e.g. a license header) is present.
+            Use --whole-line or -x to only flag a line when a search word matches the entire
+                trimmed line, rather than any substring of it.
+            Use --hidden to also walk hidden dot-directories when scanning a folder.
+                By default hidden directories and '.git' are skipped, and any patterns found in
+                '.gitignore'/'.ignore' files are honored.
+            Use '--threads=4' to set the number of worker threads used to scan files.
+                The default is the number of logical CPUs.
+            Use '--output=json' or '--output=ndjson' to print results as a JSON array or as
+                newline-delimited JSON instead of the human-readable format, for CI integration.
+            Add 'wordwarden:ignore word1,word2' at the end of a line to suppress only those
+                words on that line, and 'wordwarden:ignore-next-line' to suppress every word
+                on the line that follows.
+            Add a '.wordwarden-allow' file to allowlist known-acceptable occurrences, with one
+                entry per line formatted as 'path:line:word' or 'glob:word'.
         ",
-            args[0], args[0]
+            args[0], args[0], args[0]
         );
         process::exit(2);
     }
 
-    let mut filepaths: Vec<PathBuf> = Vec::new();
-    let mut search_strings: Vec<&String> = Vec::new();
-    let mut check_case: bool = false;
-    let mut escape: String = "wordwarden:skip-line".to_string();
+    let config = parse_args(&args[1..]);
 
-    let mut i = 1;
-    while i <= args[1..].len() {
-        let arg = &args[i];
-        let path = Path::new(&arg);
-        if path.is_file() {
-            // For better integration with pre-commit, don't check the .pre-commit-config.yaml
-            // for occurences because by the way the hook is set up, you specify the arguments
-            // to this package in that file. If we did not hardcode it here every user would
-            // need to use an escape entry in that config file.
-            if (path.file_name().unwrap() != ".pre-commit-config.yaml")
-                && (path.file_name().unwrap() != ".pre-commit-config.yml")
-            {
-                filepaths.push(path.to_path_buf())
+    let mut search_terms: Vec<SearchTerm> = Vec::new();
+    for raw in &config.search_strings {
+        match build_search_term(raw, config.check_case, config.use_regex) {
+            Ok(term) => search_terms.push(term),
+            Err(err) => {
+                eprintln!("Invalid search pattern '{}': {}", raw, err);
+                process::exit(2);
             }
-        } else if path.is_dir() {
-            match files_in_dir(path) {
-                Ok(entries) => filepaths.extend(entries),
-                _ => {} // Don't check any files that cause errors when checking if they are a file
-            };
-        } else if arg.starts_with("--casecheck") {
-            check_case = true;
-        } else if arg.starts_with("--no-casecheck") {
-            check_case = false;
-        } else if arg.starts_with("--escape=") {
-            escape = arg.replace("--escape=", "");
-        } else if arg.starts_with("-w") {
-            // Treat -w as the precursor for a word to check, append the next word to the search_strings vec
-            i += 1;
-            search_strings.push(&args[i])
-        } else {
-            search_strings.push(&arg);
         }
-        i += 1;
+    }
+
+    let worker_count = config
+        .threads
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .max(1);
+    let chunk_size = config.filepaths.len().div_ceil(worker_count).max(1);
+    let search_terms = Arc::new(search_terms);
+    let escape = Arc::new(config.escape);
+    let allowlist = Arc::new(parse_allow_file(Path::new(".wordwarden-allow")));
+    let invert = config.invert;
+    let whole_line = config.whole_line;
+    let check_case = config.check_case;
+    let use_regex = config.use_regex;
+    let output_format = config.output_format;
+
+    let mut handles = Vec::new();
+    for chunk in config.filepaths.chunks(chunk_size) {
+        let chunk = chunk.to_vec();
+        let search_terms = Arc::clone(&search_terms);
+        let escape = Arc::clone(&escape);
+        let allowlist = Arc::clone(&allowlist);
+        handles.push(thread::spawn(move || {
+            scan_chunk(
+                &chunk,
+                &search_terms,
+                &escape,
+                invert,
+                whole_line,
+                &allowlist,
+            )
+        }));
     }
 
     let mut found_any = false;
     let mut results: Vec<Occurance> = Vec::new();
-    for path in filepaths {
-        for target in &search_strings {
-            match check_file(
-                &path.to_path_buf(),
-                &mut results,
-                target,
-                check_case,
-                &escape,
-            ) {
-                Ok(found) => {
-                    if found {
-                        found_any = true;
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error reading '{}': {}", path.to_str().unwrap_or("?"), err);
-                    process::exit(2);
-                }
+    for handle in handles {
+        let (chunk_results, chunk_found) = handle.join().unwrap_or_else(|_| {
+            eprintln!("A worker thread panicked while scanning files");
+            process::exit(2);
+        });
+        results.extend(chunk_results);
+        if chunk_found {
+            found_any = true;
+        }
+    }
+
+    results.sort_by(|a, b| {
+        a.filename
+            .cmp(&b.filename)
+            .then(a.line_number.cmp(&b.line_number))
+    });
+
+    match output_format {
+        OutputFormat::Text => {
+            let extra_line_space = 1;
+            let max_line_length = &results
+                .iter()
+                .map(|r| extra_line_space + r.filename.len() + r.line_number.to_string().len())
+                .max()
+                .unwrap_or(0);
+            for result in &results {
+                let filename_and_line_number =
+                    format!("{}:{}", result.filename, result.line_number);
+                let term = build_search_term(&result.target_string, check_case, use_regex)
+                    .expect("search terms were already validated before scanning");
+                let print_line = format!(
+                    "{:<width$} -> {}",
+                    filename_and_line_number,
+                    highlight_text(&result.line_content, &term.regex),
+                    width = max_line_length
+                );
+                println!("{}", print_line);
             }
         }
+        OutputFormat::Json => {
+            println!("[");
+            for (index, result) in results.iter().enumerate() {
+                let term = build_search_term(&result.target_string, check_case, use_regex)
+                    .expect("search terms were already validated before scanning");
+                let column = if invert {
+                    None
+                } else {
+                    term.regex.find(&result.line_content).map(|m| m.start() + 1)
+                };
+                let comma = if index + 1 < results.len() { "," } else { "" };
+                println!("  {}{}", occurance_to_json(result, column), comma);
+            }
+            println!("]");
+        }
+        OutputFormat::NdJson => {
+            for result in &results {
+                let term = build_search_term(&result.target_string, check_case, use_regex)
+                    .expect("search terms were already validated before scanning");
+                let column = if invert {
+                    None
+                } else {
+                    term.regex.find(&result.line_content).map(|m| m.start() + 1)
+                };
+                println!("{}", occurance_to_json(result, column));
+            }
+        }
+    }
+
+    process::exit(if found_any { 1 } else { 0 });
+}
+
+#[cfg(test)]
+mod ignore_tests {
+    use super::*;
+
+    fn pattern(pattern: &str, negate: bool, anchored: bool) -> IgnorePattern {
+        IgnorePattern {
+            pattern: pattern.to_string(),
+            negate,
+            anchored,
+        }
+    }
+
+    #[test]
+    fn glob_match_star_matches_extension() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("*.log", "debug.log.bak"));
+        // `*` must not cross a `/`.
+        assert!(!glob_match("*.log", "logs/debug.log"));
     }
 
-    let extra_line_space = 1;
-    let max_line_length = &results
-        .iter()
-        .map(|r| (extra_line_space + r.filename.len() + r.line_number.to_string().len()))
-        .max()
-        .unwrap_or(0);
-    for result in results {
-        let filename_and_line_number = format!("{}:{}", result.filename, result.line_number);
-        let print_line = format!(
-            "{:<width$} -> {}",
-            filename_and_line_number,
-            highlight_text(&result.line_content, &result.target_string),
-            width = max_line_length
+    #[test]
+    fn anchored_pattern_only_applies_in_its_own_directory() {
+        let layers = vec![vec![pattern("sub", false, true)]];
+        // The layer that defined the anchored pattern is the directory it came from,
+        // so a same-named entry directly inside it is ignored.
+        assert!(is_ignored(&layers, "sub"));
+
+        // Once we descend into another directory, the anchored pattern from the
+        // ancestor layer no longer applies to entries down there.
+        let layers = vec![vec![pattern("sub", false, true)], vec![]];
+        assert!(!is_ignored(&layers, "sub"));
+    }
+
+    #[test]
+    fn unanchored_pattern_applies_at_any_depth() {
+        let layers = vec![vec![pattern("sub", false, false)], vec![]];
+        assert!(is_ignored(&layers, "sub"));
+    }
+
+    #[test]
+    fn negation_unignores_a_previously_matched_name() {
+        let layers = vec![vec![
+            pattern("*.log", false, false),
+            pattern("important.log", true, false),
+        ]];
+        assert!(is_ignored(&layers, "debug.log"));
+        assert!(!is_ignored(&layers, "important.log"));
+    }
+}
+
+#[cfg(test)]
+mod search_term_tests {
+    use super::*;
+
+    #[test]
+    fn literal_mode_matches_substring_case_insensitively_by_default() {
+        let term = build_search_term("TODO", false, false).unwrap();
+        assert!(term_matches(&term, "a todo here", false));
+        assert!(!term_matches(&term, "nothing here", false));
+    }
+
+    #[test]
+    fn casecheck_makes_matching_case_sensitive() {
+        let term = build_search_term("TODO", true, false).unwrap();
+        assert!(term_matches(&term, "a TODO here", false));
+        assert!(!term_matches(&term, "a todo here", false));
+    }
+
+    #[test]
+    fn regex_mode_compiles_raw_as_a_pattern() {
+        let term = build_search_term(r"TODO\(.*\)", false, true).unwrap();
+        assert!(term_matches(&term, "TODO(alice): fix this", false));
+        assert!(!term_matches(&term, "TODO without parens", false));
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        assert!(build_search_term("(unclosed", false, true).is_err());
+    }
+
+    #[test]
+    fn whole_line_requires_the_match_to_span_the_trimmed_line() {
+        let term = build_search_term("TODO", false, false).unwrap();
+        assert!(term_matches(&term, "  TODO  ", true));
+        assert!(!term_matches(&term, "a TODO here", true));
+    }
+}
+
+#[cfg(test)]
+mod allow_and_directive_tests {
+    use super::*;
+
+    #[test]
+    fn parse_allow_line_reads_exact_path_line_word() {
+        match parse_allow_line("src/main.rs:42:todo") {
+            Some(AllowEntry::Exact { path, line, word }) => {
+                assert_eq!(path, "src/main.rs");
+                assert_eq!(line, 42);
+                assert_eq!(word, "todo");
+            }
+            other => panic!("expected an Exact entry, got {}", other.is_some()),
+        }
+    }
+
+    #[test]
+    fn parse_allow_line_reads_glob_word_pair() {
+        match parse_allow_line("*.md:todo") {
+            Some(AllowEntry::Glob { glob, word }) => {
+                assert_eq!(glob, "*.md");
+                assert_eq!(word, "todo");
+            }
+            other => panic!("expected a Glob entry, got {}", other.is_some()),
+        }
+    }
+
+    #[test]
+    fn parse_allow_line_rejects_malformed_lines() {
+        assert!(parse_allow_line("no-colon-here").is_none());
+    }
+
+    #[test]
+    fn is_allowed_matches_exact_and_glob_entries() {
+        let allowlist = vec![
+            AllowEntry::Exact {
+                path: "src/main.rs".to_string(),
+                line: 10,
+                word: "todo".to_string(),
+            },
+            AllowEntry::Glob {
+                glob: "*.md".to_string(),
+                word: "todo".to_string(),
+            },
+        ];
+        assert!(is_allowed(&allowlist, "src/main.rs", 10, "todo"));
+        assert!(!is_allowed(&allowlist, "src/main.rs", 11, "todo"));
+        assert!(is_allowed(&allowlist, "README.md", 1, "todo"));
+        assert!(!is_allowed(&allowlist, "README.md", 1, "fixme"));
+    }
+
+    #[test]
+    fn line_directives_reads_ignore_next_line() {
+        let (skip_next, words) = line_directives("// wordwarden:ignore-next-line");
+        assert!(skip_next);
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn line_directives_reads_ignore_word_list() {
+        let (skip_next, words) = line_directives("todo, fixme // wordwarden:ignore todo, fixme");
+        assert!(!skip_next);
+        assert_eq!(words, vec!["todo".to_string(), "fixme".to_string()]);
+    }
+
+    #[test]
+    fn line_directives_reads_no_directive_as_empty() {
+        let (skip_next, words) = line_directives("just a normal line");
+        assert!(!skip_next);
+        assert!(words.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    #[test]
+    fn json_escape_escapes_control_and_special_characters() {
+        assert_eq!(
+            json_escape("quote\" backslash\\"),
+            "quote\\\" backslash\\\\"
         );
-        println!("{}", print_line);
+        assert_eq!(json_escape("line\nreturn\rtab\t"), "line\\nreturn\\rtab\\t");
     }
 
-    process::exit(if found_any { 1 } else { 0 });
+    #[test]
+    fn occurance_to_json_includes_column_when_given() {
+        let occurance = Occurance {
+            filename: "src/main.rs".to_string(),
+            line_number: 3,
+            target_string: "todo".to_string(),
+            line_content: "// todo: fix".to_string(),
+        };
+        assert_eq!(
+            occurance_to_json(&occurance, Some(4)),
+            "{\"filename\":\"src/main.rs\",\"line_number\":3,\"target_string\":\"todo\",\"column\":4,\"line_content\":\"// todo: fix\"}"
+        );
+        assert_eq!(
+            occurance_to_json(&occurance, None),
+            "{\"filename\":\"src/main.rs\",\"line_number\":3,\"target_string\":\"todo\",\"column\":null,\"line_content\":\"// todo: fix\"}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod parse_args_tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    /// A real, uniquely-named file so `Operand::Auto` resolution (which checks the
+    /// filesystem) treats it as a path rather than a search word.
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "wordwarden_parse_args_test_{}_{}",
+            process::id(),
+            name
+        ));
+        std::fs::write(&path, "content\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn dash_w_forces_the_next_argument_to_be_a_search_word() {
+        let file = temp_file("dash_w");
+        let config = parse_args(&args(&["-w", "examples", file.to_str().unwrap()]));
+        std::fs::remove_file(&file).ok();
+        assert_eq!(config.search_strings, vec!["examples".to_string()]);
+        assert_eq!(config.filepaths, vec![file]);
+    }
+
+    #[test]
+    fn double_dash_treats_everything_after_it_as_search_words() {
+        let file = temp_file("double_dash");
+        let config = parse_args(&args(&[file.to_str().unwrap(), "--", "examples", "-v"]));
+        std::fs::remove_file(&file).ok();
+        assert_eq!(config.filepaths, vec![file]);
+        assert_eq!(
+            config.search_strings,
+            vec!["examples".to_string(), "-v".to_string()]
+        );
+        // `-v` came after `--`, so it must not have been parsed as `--invert`.
+        assert!(!config.invert);
+    }
+
+    #[test]
+    fn threads_flag_parses_a_positive_integer() {
+        let config = parse_args(&args(&["--threads=4", "word"]));
+        assert_eq!(config.threads, Some(4));
+    }
+
+    #[test]
+    fn output_flag_selects_the_requested_format() {
+        let config = parse_args(&args(&["--output=ndjson", "word"]));
+        assert!(config.output_format == OutputFormat::NdJson);
+
+        let config = parse_args(&args(&["--output=json", "word"]));
+        assert!(config.output_format == OutputFormat::Json);
+    }
+
+    #[test]
+    fn an_operand_that_is_not_a_real_path_is_treated_as_a_search_word() {
+        let config = parse_args(&args(&["definitely-not-a-real-path-on-disk"]));
+        assert_eq!(
+            config.search_strings,
+            vec!["definitely-not-a-real-path-on-disk".to_string()]
+        );
+        assert!(config.filepaths.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod check_file_invert_tests {
+    use super::*;
+
+    #[test]
+    fn invert_flags_a_line_only_when_none_of_the_targets_are_present() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("wordwarden_check_file_test_{}.txt", process::id()));
+        std::fs::write(
+            &path,
+            "Copyright Example Corp\nLicensed under MIT\nplain text\n",
+        )
+        .unwrap();
+
+        let terms = vec![
+            build_search_term("Copyright", false, false).unwrap(),
+            build_search_term("Licensed", false, false).unwrap(),
+        ];
+        let allowlist: Vec<AllowEntry> = Vec::new();
+        let mut results = Vec::new();
+        check_file(
+            &path,
+            &mut results,
+            &terms,
+            "wordwarden:skip-line",
+            true,
+            false,
+            &allowlist,
+        )
+        .unwrap();
+
+        std::fs::remove_file(&path).ok();
+
+        // Lines 1 and 2 each contain one of the two required words, so neither should be
+        // flagged; only the line with neither is missing both.
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].line_number, 3);
+    }
 }